@@ -0,0 +1,1246 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+//! Core Brainfuck interpreter: parsing, the optimizing IR, the tape, and
+//! the tree-walking executor. Only needs `alloc` for its `Vec`/`String`
+//! buffers, so it works in `no_std` contexts (embedded, bare metal) as
+//! long as a global allocator is provided. `Input`/`Output` are left
+//! abstract so the host supplies its own byte source/sink (stdin/stdout
+//! under the `std` feature, or e.g. a UART on bare metal).
+
+extern crate alloc;
+
+use alloc::{collections::BTreeMap, format, string::String, vec, vec::Vec};
+use core::{convert::TryFrom, fmt};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Command {
+    IncPtr,
+    DecPtr,
+    Inc,
+    Dec,
+    Output,
+    Input,
+    JmpStart(usize),
+    JmpEnd(usize),
+}
+
+impl TryFrom<&u8> for Command {
+    type Error = Error;
+
+    fn try_from(value: &u8) -> Result<Self, Self::Error> {
+        let cmd = match value {
+            b'>' => Self::IncPtr,
+            b'<' => Self::DecPtr,
+            b'+' => Self::Inc,
+            b'-' => Self::Dec,
+            b'.' => Self::Output,
+            b',' => Self::Input,
+            b'[' => Self::JmpStart(0),
+            b']' => Self::JmpEnd(0),
+            a => {
+                return Err(Error::InvalidCommand {
+                    location: Location::default(),
+                    command: *a,
+                })
+            }
+        };
+
+        Ok(cmd)
+    }
+}
+
+/// A single step of the coalesced, optimized instruction stream produced by
+/// [`Program::load`].
+///
+/// Unlike [`Command`], which mirrors the source one byte-op at a time, an
+/// `Op` may represent a whole run of source commands (e.g. `++++++++`
+/// becomes a single `Add`), or a pattern recognized by the optimizer (e.g.
+/// `[-]` becomes a single `Set`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    /// Add `n` (wrapping, at the configured [`CellWidth`]) to the cell
+    /// under the pointer. Coalesced from a run of `Inc`/`Dec`; `n` is the
+    /// run's raw, un-reduced delta, since the wrapping modulus isn't known
+    /// until [`Brainfuck::run`]/[`Brainfuck::run_async`] picks a
+    /// [`CellWidth`].
+    Add(i32),
+    /// Move the pointer by `n` cells. Coalesced from a run of
+    /// `IncPtr`/`DecPtr`.
+    Move(isize),
+    /// Set the cell under the pointer to a fixed value, e.g. from
+    /// collapsing a `[-]`/`[+]` loop.
+    Set(u8),
+    Output,
+    Input,
+    /// Jump to `matching` (the index of the loop's `LoopEnd`) if the
+    /// current cell is zero.
+    LoopStart(usize),
+    /// Jump to `matching` (the loop's start) if the current cell is
+    /// nonzero.
+    LoopEnd(usize),
+    /// Add `factor * current_cell` (wrapping, at the configured
+    /// [`CellWidth`]) to the cell at `offset` from the pointer, without
+    /// moving the pointer. Emitted for copy/multiply loops such as
+    /// `[->+<]`; like [`Op::Add`], `factor` is left un-reduced until run
+    /// time.
+    MulAdd { offset: isize, factor: i32 },
+}
+
+/// Intermediate tree form used while optimizing a [`Command`] stream into
+/// [`Op`]s. Loops are nested recursively so peephole rewrites can inspect a
+/// loop's whole body before it is flattened into the final, indexed `Op`
+/// stream.
+#[derive(Debug, Clone)]
+enum Node {
+    Add(i32),
+    Move(isize),
+    Set(u8),
+    Output,
+    Input,
+    MulAdd { offset: isize, factor: i32 },
+    Loop(Vec<Node>),
+}
+
+impl Node {
+    /// Parses `commands[*pos..]` into a flat list of (coalesced) nodes,
+    /// recursing into `Loop` nodes for balanced `JmpStart`/`JmpEnd` pairs.
+    /// Stops at the end of `commands` or at an unconsumed `JmpEnd`,
+    /// returning control to the caller that opened the enclosing loop.
+    fn parse(commands: &[Command], pos: &mut usize) -> Vec<Node> {
+        let mut nodes = Vec::new();
+
+        while *pos < commands.len() {
+            match commands[*pos] {
+                Command::Inc | Command::Dec => {
+                    let mut delta: i32 = 0;
+                    while *pos < commands.len()
+                        && matches!(commands[*pos], Command::Inc | Command::Dec)
+                    {
+                        delta += if commands[*pos] == Command::Inc { 1 } else { -1 };
+                        *pos += 1;
+                    }
+                    nodes.push(Node::Add(delta));
+                }
+                Command::IncPtr | Command::DecPtr => {
+                    let mut delta: isize = 0;
+                    while *pos < commands.len()
+                        && matches!(commands[*pos], Command::IncPtr | Command::DecPtr)
+                    {
+                        delta += if commands[*pos] == Command::IncPtr {
+                            1
+                        } else {
+                            -1
+                        };
+                        *pos += 1;
+                    }
+                    nodes.push(Node::Move(delta));
+                }
+                Command::Output => {
+                    nodes.push(Node::Output);
+                    *pos += 1;
+                }
+                Command::Input => {
+                    nodes.push(Node::Input);
+                    *pos += 1;
+                }
+                Command::JmpStart(_) => {
+                    *pos += 1;
+                    let body = Node::parse(commands, pos);
+                    // Safety: balanced by `Program::load`, so a `JmpStart`
+                    // always has a matching `JmpEnd` to stop the recursion.
+                    *pos += 1;
+                    nodes.extend(Self::collapse_loop(body));
+                }
+                Command::JmpEnd(_) => break,
+            }
+        }
+
+        nodes
+    }
+
+    /// Applies the standard peephole rewrites to a loop body, returning the
+    /// nodes that should replace the whole loop (brackets included).
+    fn collapse_loop(body: Vec<Node>) -> Vec<Node> {
+        // `[-]` / `[+]`: zero the current cell.
+        if let [Node::Add(n)] = body.as_slice() {
+            if *n == 1 || *n == -1 {
+                return vec![Node::Set(0)];
+            }
+        }
+
+        // Copy/multiply loop, e.g. `[->+<]`: the body only moves the
+        // pointer and adds to cells, ends where it started, and decrements
+        // the control cell by exactly one per iteration.
+        if let Some(muls) = Self::try_mul_loop(&body) {
+            let mut nodes: Vec<Node> = muls
+                .into_iter()
+                .map(|(offset, factor)| Node::MulAdd { offset, factor })
+                .collect();
+            nodes.push(Node::Set(0));
+            return nodes;
+        }
+
+        vec![Node::Loop(body)]
+    }
+
+    /// Scans a loop body for the copy/multiply-loop shape, returning the
+    /// per-offset factors to apply (relative to the pointer position at
+    /// loop entry) if it matches, or `None` if the body does anything else
+    /// (I/O, a nested loop, or doesn't return the pointer to its start).
+    fn try_mul_loop(body: &[Node]) -> Option<Vec<(isize, i32)>> {
+        let mut cursor: isize = 0;
+        let mut deltas: BTreeMap<isize, i32> = BTreeMap::new();
+
+        for node in body {
+            match node {
+                Node::Add(n) => *deltas.entry(cursor).or_insert(0) += *n,
+                Node::Move(n) => cursor += n,
+                _ => return None,
+            }
+        }
+
+        if cursor != 0 || deltas.get(&0).copied().unwrap_or(0) != -1 {
+            return None;
+        }
+
+        Some(
+            deltas
+                .into_iter()
+                .filter(|(offset, _)| *offset != 0)
+                .filter(|(_, factor)| *factor != 0)
+                .collect(),
+        )
+    }
+
+    /// Flattens a tree of nodes into the final `Op` stream, resolving
+    /// `LoopStart`/`LoopEnd` indices the same way `Program::load` resolves
+    /// `JmpStart`/`JmpEnd`.
+    fn flatten(nodes: Vec<Node>, ops: &mut Vec<Op>) {
+        for node in nodes {
+            match node {
+                Node::Add(n) => ops.push(Op::Add(n)),
+                Node::Move(n) => ops.push(Op::Move(n)),
+                Node::Set(n) => ops.push(Op::Set(n)),
+                Node::Output => ops.push(Op::Output),
+                Node::Input => ops.push(Op::Input),
+                Node::MulAdd { offset, factor } => ops.push(Op::MulAdd { offset, factor }),
+                Node::Loop(body) => {
+                    let start = ops.len();
+                    ops.push(Op::LoopStart(0));
+                    Self::flatten(body, ops);
+                    let end = ops.len();
+                    ops.push(Op::LoopEnd(start));
+
+                    if let Some(Op::LoopStart(index)) = ops.get_mut(start) {
+                        *index = end;
+                    } else {
+                        unreachable!("op vec is broken");
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Cell width, selecting the wrapping modulus for `Tape::add`/`Tape::set`.
+///
+/// The optimizer coalesces a run of consecutive `+`/`-` into a single
+/// [`Op::Add`] carrying the run's raw delta (see [`Node`]); it does not
+/// reduce that delta mod 256, since at `Program::load` time the cell
+/// width isn't known yet. Wrapping only happens here, in `Tape::add`,
+/// once a `CellWidth` has actually been chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellWidth {
+    U8,
+    U16,
+    U32,
+}
+
+impl CellWidth {
+    fn mask(self) -> u32 {
+        match self {
+            Self::U8 => u8::MAX as u32,
+            Self::U16 => u16::MAX as u32,
+            Self::U32 => u32::MAX,
+        }
+    }
+}
+
+/// How the pointer behaves when it moves past either end of the tape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BoundsMode {
+    /// Fixed-size tape of `size` cells; the pointer wraps around at either
+    /// end.
+    Wrap { size: usize },
+    /// Fixed-size tape of `size` cells; moving past either end is a
+    /// [`RuntimeError::PointerOutOfBounds`].
+    Error { size: usize },
+    /// Unbounded: the tape grows as the pointer moves past the end.
+    /// Moving before the start is still a
+    /// [`RuntimeError::PointerOutOfBounds`], since there's nothing to grow
+    /// into.
+    Grow,
+}
+
+/// What `,` does when there is no more input left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EofPolicy {
+    /// Leave the current cell untouched.
+    Unchanged,
+    /// Set the current cell to 0.
+    Zero,
+    /// Set the current cell to its maximum value for the configured
+    /// [`CellWidth`].
+    Max,
+}
+
+/// Tape semantics for [`Brainfuck::run`]/[`Brainfuck::run_async`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TapeOptions {
+    pub bounds: BoundsMode,
+    pub cell_width: CellWidth,
+    pub eof: EofPolicy,
+}
+
+impl Default for TapeOptions {
+    /// An auto-growing tape of 8-bit cells that leaves a cell untouched on
+    /// EOF.
+    fn default() -> Self {
+        Self {
+            bounds: BoundsMode::Grow,
+            cell_width: CellWidth::U8,
+            eof: EofPolicy::Unchanged,
+        }
+    }
+}
+
+/// An error raised while executing a [`Program`], as opposed to [`Error`]
+/// which is raised while loading one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeError {
+    /// The pointer moved to `pointer`, which falls outside the tape's
+    /// configured [`BoundsMode`].
+    PointerOutOfBounds { pointer: isize },
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PointerOutOfBounds { pointer } => {
+                write!(f, "pointer moved out of bounds to {}", pointer)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for RuntimeError {}
+
+#[derive(Debug, Clone)]
+pub struct Tape {
+    inner: Vec<u32>,
+    options: TapeOptions,
+}
+
+impl Default for Tape {
+    fn default() -> Self {
+        Self::new(TapeOptions::default())
+    }
+}
+
+impl Tape {
+    pub fn new(options: TapeOptions) -> Self {
+        let inner = match options.bounds {
+            BoundsMode::Wrap { size } | BoundsMode::Error { size } => vec![0; size],
+            BoundsMode::Grow => Vec::new(),
+        };
+
+        Self { inner, options }
+    }
+
+    /// Resolves a (possibly out-of-range) pointer to a valid tape index
+    /// according to the configured [`BoundsMode`], growing the tape first
+    /// if it is allowed to.
+    fn resolve(&mut self, pointer: isize) -> Result<usize, RuntimeError> {
+        match self.options.bounds {
+            BoundsMode::Wrap { size } => {
+                // A zero-size tape has no cells to wrap onto; `size` as
+                // isize would also make the rem_euclid below divide by
+                // zero. Same verdict `Error { size: 0 }` already gives
+                // every pointer.
+                if size == 0 {
+                    return Err(RuntimeError::PointerOutOfBounds { pointer });
+                }
+
+                Ok(pointer.rem_euclid(size as isize) as usize)
+            }
+            BoundsMode::Error { size } => {
+                if pointer < 0 || pointer as usize >= size {
+                    Err(RuntimeError::PointerOutOfBounds { pointer })
+                } else {
+                    Ok(pointer as usize)
+                }
+            }
+            BoundsMode::Grow => {
+                if pointer < 0 {
+                    return Err(RuntimeError::PointerOutOfBounds { pointer });
+                }
+
+                let index = pointer as usize;
+                if index >= self.inner.len() {
+                    self.inner.resize(index + 1, 0);
+                }
+
+                Ok(index)
+            }
+        }
+    }
+
+    pub fn inc(&mut self, index: usize) {
+        self.add(index, 1);
+    }
+
+    pub fn dec(&mut self, index: usize) {
+        self.add(index, -1);
+    }
+
+    /// Adds `delta` to the cell at `index` in a single step, wrapping at
+    /// the configured [`CellWidth`].
+    pub fn add(&mut self, index: usize, delta: i32) {
+        let mask = self.options.cell_width.mask();
+        self.inner[index] = self.inner[index].wrapping_add(delta as u32) & mask;
+    }
+
+    pub fn set(&mut self, index: usize, value: u32) {
+        self.inner[index] = value & self.options.cell_width.mask();
+    }
+
+    pub fn get(&self, index: usize) -> u32 {
+        self.inner.get(index).copied().unwrap_or(0)
+    }
+
+    /// Applies the configured [`EofPolicy`] to the cell at `index`.
+    pub fn eof(&mut self, index: usize) {
+        match self.options.eof {
+            EofPolicy::Unchanged => {}
+            EofPolicy::Zero => self.set(index, 0),
+            EofPolicy::Max => self.set(index, self.options.cell_width.mask()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Program {
+    commands: Vec<Command>,
+    ops: Vec<Op>,
+}
+
+/// A position in the original source, tracked alongside the raw byte
+/// offset so errors can point at a specific line/column instead of a flat
+/// index into the file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    /// 1-based line number.
+    pub line: usize,
+    /// 1-based column number (in bytes, not grapheme clusters).
+    pub column: usize,
+    /// 0-based byte offset into the source, as `index` used to be.
+    pub byte: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {} (byte {})",
+            self.line, self.column, self.byte
+        )
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Error {
+    InvalidCommand {
+        location: Location,
+        command: u8,
+    },
+    UnmatchedJump {
+        location: Location,
+    },
+}
+
+impl Error {
+    pub(crate) fn set_location(&mut self, loc: Location) {
+        match self {
+            Self::InvalidCommand { location, .. } => {
+                *location = loc;
+            }
+            Self::UnmatchedJump { location, .. } => {
+                *location = loc;
+            }
+        }
+    }
+
+    /// Renders this error together with the offending line from `source`
+    /// and a caret under the column, e.g.:
+    ///
+    /// ```text
+    /// No matching jump found for jump at line 1, column 1 (byte 0)
+    ///   --> 1:1
+    ///    | [[]
+    ///    | ^
+    /// ```
+    pub fn report(&self, source: impl AsRef<[u8]>) -> String {
+        let location = match self {
+            Self::InvalidCommand { location, .. } => *location,
+            Self::UnmatchedJump { location, .. } => *location,
+        };
+
+        let line = source
+            .as_ref()
+            .split(|&b| b == b'\n')
+            .nth(location.line.saturating_sub(1))
+            .unwrap_or(&[]);
+        let line = String::from_utf8_lossy(line);
+
+        format!(
+            "{self}\n  --> {line}:{column}\n   | {src}\n   | {caret}^\n",
+            self = self,
+            line = location.line,
+            column = location.column,
+            src = line,
+            caret = " ".repeat(location.column.saturating_sub(1)),
+        )
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidCommand { location, command } => write!(
+                f,
+                "Found invalid command `{}` (code: {}) at {}",
+                *command as char, command, location
+            ),
+            Self::UnmatchedJump { location } => {
+                write!(f, "No matching jump found for jump at {}", location)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for Error {}
+
+impl Program {
+    pub fn load(data: impl AsRef<[u8]>) -> Result<Self, Error> {
+        let data = data.as_ref();
+
+        let mut jump_stack: Vec<(usize, Location)> = Vec::new();
+        let mut commands: Vec<Command> = Vec::with_capacity(data.len());
+
+        let mut line = 1;
+        let mut column = 1;
+
+        for (b_loc, b) in data.iter().enumerate() {
+            let location = Location {
+                line,
+                column,
+                byte: b_loc,
+            };
+
+            if *b == b'\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+
+            // make relaxed version
+            if b.is_ascii_whitespace() {
+                continue;
+            }
+
+            let mut command = Command::try_from(b).map_err(|mut err| {
+                err.set_location(location);
+                err
+            })?;
+
+            match &mut command {
+                Command::JmpStart(_) => {
+                    jump_stack.push((commands.len(), location));
+                }
+                Command::JmpEnd(index) => {
+                    let idx = commands.len();
+
+                    let (matching, _) =
+                        jump_stack.pop().ok_or(Error::UnmatchedJump { location })?;
+
+                    *index = matching;
+
+                    if let Some(Command::JmpStart(index)) = commands.get_mut(matching) {
+                        *index = idx;
+                    } else {
+                        unreachable!("command vec is broken");
+                    }
+                }
+                _ => {}
+            }
+
+            commands.push(command);
+        }
+
+        if !jump_stack.is_empty() {
+            // Safety: Checked the len in if.
+            let (_, unmatched) = jump_stack.pop().unwrap();
+
+            Err(Error::UnmatchedJump {
+                location: unmatched,
+            })
+        } else {
+            let ops = Self::optimize(&commands);
+
+            Ok(Self { commands, ops })
+        }
+    }
+
+    /// Lowers a resolved `Command` stream into a coalesced, peephole-
+    /// optimized `Op` stream. See [`Node`] for the intermediate form used
+    /// to apply the rewrites.
+    fn optimize(commands: &[Command]) -> Vec<Op> {
+        let mut pos = 0;
+        let nodes = Node::parse(commands, &mut pos);
+
+        let mut ops = Vec::new();
+        Node::flatten(nodes, &mut ops);
+
+        ops
+    }
+
+    /// Emits standalone source code equivalent to this program, for
+    /// compiling ahead-of-time instead of interpreting. Translates the raw
+    /// `Command` stream one-to-one, turning each resolved jump into a
+    /// labeled conditional branch.
+    pub fn transpile(&self, target: Target) -> String {
+        match target {
+            Target::C => self.transpile_c(),
+            Target::Asm => self.transpile_asm(),
+        }
+    }
+
+    fn transpile_c(&self) -> String {
+        let mut out = String::new();
+        out.push_str(
+            "#include <stdio.h>\n\nstatic unsigned char tape[30000];\n\nint main(void) {\n    unsigned char *ptr = tape;\n\n",
+        );
+
+        for (i, command) in self.commands.iter().enumerate() {
+            match command {
+                Command::IncPtr => out.push_str("    ++ptr;\n"),
+                Command::DecPtr => out.push_str("    --ptr;\n"),
+                Command::Inc => out.push_str("    ++*ptr;\n"),
+                Command::Dec => out.push_str("    --*ptr;\n"),
+                Command::Output => out.push_str("    putchar(*ptr);\n"),
+                Command::Input => out.push_str("    *ptr = (unsigned char)getchar();\n"),
+                Command::JmpStart(matching) => {
+                    out.push_str(&format!("L{}: if (!*ptr) goto L{};\n", i, matching))
+                }
+                Command::JmpEnd(matching) => {
+                    out.push_str(&format!("L{}: if (*ptr) goto L{};\n", i, matching))
+                }
+            }
+        }
+
+        out.push_str(&format!("L{}:\n    return 0;\n}}\n", self.commands.len()));
+
+        out
+    }
+
+    fn transpile_asm(&self) -> String {
+        let mut out = String::new();
+        out.push_str(
+            "section .bss\ntape: resb 30000\n\nsection .text\nglobal _start\n_start:\n    lea r12, [rel tape]\n\n",
+        );
+
+        for (i, command) in self.commands.iter().enumerate() {
+            match command {
+                Command::IncPtr => out.push_str(&format!("L{}:\n    add r12, 1\n", i)),
+                Command::DecPtr => out.push_str(&format!("L{}:\n    sub r12, 1\n", i)),
+                Command::Inc => out.push_str(&format!("L{}:\n    inc byte [r12]\n", i)),
+                Command::Dec => out.push_str(&format!("L{}:\n    dec byte [r12]\n", i)),
+                Command::Output => out.push_str(&format!(
+                    "L{}:\n    mov rax, 1\n    mov rdi, 1\n    mov rsi, r12\n    mov rdx, 1\n    syscall\n",
+                    i
+                )),
+                Command::Input => out.push_str(&format!(
+                    "L{}:\n    mov rax, 0\n    mov rdi, 0\n    mov rsi, r12\n    mov rdx, 1\n    syscall\n",
+                    i
+                )),
+                Command::JmpStart(matching) => out.push_str(&format!(
+                    "L{}:\n    cmp byte [r12], 0\n    je L{}\n",
+                    i, matching
+                )),
+                Command::JmpEnd(matching) => out.push_str(&format!(
+                    "L{}:\n    cmp byte [r12], 0\n    jne L{}\n",
+                    i, matching
+                )),
+            }
+        }
+
+        out.push_str(&format!(
+            "L{}:\n    mov rax, 60\n    xor rdi, rdi\n    syscall\n",
+            self.commands.len()
+        ));
+
+        out
+    }
+}
+
+/// Compilation target for [`Program::transpile`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// Standalone C source, compilable with any C99 compiler.
+    C,
+    /// x86-64 assembly in NASM syntax, targeting Linux (uses raw
+    /// `write`/`read`/`exit` syscalls instead of libc).
+    Asm,
+}
+
+pub trait Input: Iterator<Item = u8> {}
+
+pub trait Output {
+    fn write(&mut self, value: u8);
+}
+
+/// Non-blocking counterpart to [`Input`], for sources that yield instead
+/// of blocking the calling task while waiting for a byte (async stdin, a
+/// socket, a pipe).
+#[allow(async_fn_in_trait)]
+pub trait AsyncInput {
+    async fn next(&mut self) -> Option<u8>;
+}
+
+/// Non-blocking counterpart to [`Output`].
+#[allow(async_fn_in_trait)]
+pub trait AsyncOutput {
+    async fn write(&mut self, value: u8);
+}
+
+/// Reads input from stdin: each call reads one whole line and yields only
+/// its first byte; the rest of that line is discarded.
+#[cfg(feature = "std")]
+pub struct StdinInput;
+
+#[cfg(feature = "std")]
+impl Iterator for StdinInput {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = String::new();
+        ::std::io::stdin().read_line(&mut line).ok();
+        line.bytes().next()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Input for StdinInput {}
+
+#[cfg(feature = "std")]
+impl AsyncInput for StdinInput {
+    async fn next(&mut self) -> Option<u8> {
+        Iterator::next(self)
+    }
+}
+
+#[cfg(feature = "std")]
+pub struct StdoutOutput;
+
+#[cfg(feature = "std")]
+impl Output for StdoutOutput {
+    fn write(&mut self, value: u8) {
+        use std::io::Write as _;
+
+        let stdout = ::std::io::stdout();
+        let mut stdout = stdout.lock();
+        // Write and flush on stdout should never fail.
+        let _ = stdout.write(&[value]).expect("stdout write failed");
+        stdout.flush().expect("stdout flush failed");
+    }
+}
+
+#[cfg(feature = "std")]
+impl AsyncOutput for StdoutOutput {
+    async fn write(&mut self, value: u8) {
+        Output::write(self, value);
+    }
+}
+
+/// Applies every [`Op`] except [`Op::Output`]/[`Op::Input`] to `tape`,
+/// advancing or branching `d_ptr` as needed.
+///
+/// Output and input are left to the caller since [`Brainfuck::run`] and
+/// [`Brainfuck::run_async`] need to block or await on them respectively;
+/// every other step is identical between the two, so it lives here once.
+/// Returns the index `i_ptr` should jump to for `Op::LoopStart`/
+/// `Op::LoopEnd`, or `None` if the caller should just advance past `op`
+/// as usual.
+fn step(op: Op, tape: &mut Tape, d_ptr: &mut isize) -> Result<Option<usize>, RuntimeError> {
+    match op {
+        Op::Add(n) => {
+            let index = tape.resolve(*d_ptr)?;
+            tape.add(index, n);
+        }
+        Op::Move(n) => {
+            *d_ptr = d_ptr
+                .checked_add(n)
+                .ok_or(RuntimeError::PointerOutOfBounds { pointer: *d_ptr })?;
+            // Validate eagerly so an out-of-bounds move is reported even
+            // if the program never goes on to read or write the cell it
+            // landed on.
+            tape.resolve(*d_ptr)?;
+        }
+        Op::Set(value) => {
+            let index = tape.resolve(*d_ptr)?;
+            tape.set(index, value as u32);
+        }
+        Op::LoopStart(matching) => {
+            let index = tape.resolve(*d_ptr)?;
+            if tape.get(index) == 0 {
+                return Ok(Some(matching));
+            }
+        }
+        Op::LoopEnd(matching) => {
+            let index = tape.resolve(*d_ptr)?;
+            if tape.get(index) != 0 {
+                return Ok(Some(matching));
+            }
+        }
+        Op::MulAdd { offset, factor } => {
+            let index = tape.resolve(*d_ptr)?;
+            let target = d_ptr
+                .checked_add(offset)
+                .ok_or(RuntimeError::PointerOutOfBounds { pointer: *d_ptr })?;
+            let target = tape.resolve(target)?;
+            let value = tape.get(index) as i64 * factor as i64;
+            tape.add(target, value as i32);
+        }
+        Op::Output | Op::Input => unreachable!("handled directly by run/run_async"),
+    }
+
+    Ok(None)
+}
+
+pub struct Brainfuck<I, O> {
+    input: I,
+    output: O,
+}
+
+#[cfg(feature = "std")]
+impl Default for Brainfuck<StdinInput, StdoutOutput> {
+    fn default() -> Self {
+        Self {
+            input: StdinInput,
+            output: StdoutOutput,
+        }
+    }
+}
+
+impl<I, O> Brainfuck<I, O>
+where
+    I: Input,
+    O: Output,
+{
+    /// Executes `program` on a fresh [`Tape`] configured by `options`.
+    ///
+    /// Returns a [`RuntimeError`] if the pointer moves outside the tape's
+    /// configured [`BoundsMode`] instead of panicking.
+    pub fn run(&mut self, program: &Program, options: TapeOptions) -> Result<(), RuntimeError> {
+        let ops = &program.ops;
+        let mut tape = Tape::new(options);
+        let mut d_ptr: isize = 0;
+        let mut i_ptr: usize = 0;
+
+        while i_ptr < ops.len() {
+            let op = ops[i_ptr];
+
+            match op {
+                Op::Output => {
+                    let index = tape.resolve(d_ptr)?;
+                    self.output.write(tape.get(index) as u8);
+                }
+                Op::Input => {
+                    let index = tape.resolve(d_ptr)?;
+                    match self.input.next() {
+                        Some(value) => tape.set(index, value as u32),
+                        None => tape.eof(index),
+                    }
+                }
+                _ => {
+                    if let Some(target) = step(op, &mut tape, &mut d_ptr)? {
+                        i_ptr = target;
+                        continue;
+                    }
+                }
+            }
+
+            i_ptr += 1;
+        }
+
+        Ok(())
+    }
+
+    pub fn output(&self) -> &O {
+        &self.output
+    }
+
+    pub fn output_mut(&mut self) -> &mut O {
+        &mut self.output
+    }
+
+    pub fn input(&self) -> &I {
+        &self.input
+    }
+
+    pub fn input_mut(&mut self) -> &mut I {
+        &mut self.input
+    }
+
+    pub fn into_inner(self) -> (I, O) {
+        (self.input, self.output)
+    }
+}
+
+impl<I, O> Brainfuck<I, O>
+where
+    I: AsyncInput,
+    O: AsyncOutput,
+{
+    /// Same semantics as [`Brainfuck::run`], but awaits at `Op::Input`/
+    /// `Op::Output` steps instead of blocking, so a program can be driven
+    /// by an async runtime and interleaved with other I/O-bound work.
+    pub async fn run_async(
+        &mut self,
+        program: &Program,
+        options: TapeOptions,
+    ) -> Result<(), RuntimeError> {
+        let ops = &program.ops;
+        let mut tape = Tape::new(options);
+        let mut d_ptr: isize = 0;
+        let mut i_ptr: usize = 0;
+
+        while i_ptr < ops.len() {
+            let op = ops[i_ptr];
+
+            match op {
+                Op::Output => {
+                    let index = tape.resolve(d_ptr)?;
+                    self.output.write(tape.get(index) as u8).await;
+                }
+                Op::Input => {
+                    let index = tape.resolve(d_ptr)?;
+                    match self.input.next().await {
+                        Some(value) => tape.set(index, value as u32),
+                        None => tape.eof(index),
+                    }
+                }
+                _ => {
+                    if let Some(target) = step(op, &mut tape, &mut d_ptr)? {
+                        i_ptr = target;
+                        continue;
+                    }
+                }
+            }
+
+            i_ptr += 1;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use core::{
+        future::Future,
+        pin::Pin,
+        task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+    };
+
+    /// Minimal executor for these tests: every future driven here (sync
+    /// I/O wrapped in an immediately-ready `async fn`) completes on its
+    /// first poll, so a no-op waker is enough.
+    fn block_on<F: Future>(fut: F) -> F::Output {
+        fn noop(_: *const ()) {}
+        fn clone(_: *const ()) -> RawWaker {
+            RawWaker::new(core::ptr::null(), &VTABLE)
+        }
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+
+        let waker = unsafe { Waker::from_raw(RawWaker::new(core::ptr::null(), &VTABLE)) };
+        let mut cx = Context::from_waker(&waker);
+
+        let mut fut = fut;
+        // Safety: `fut` is not moved again after being pinned.
+        let mut fut = unsafe { Pin::new_unchecked(&mut fut) };
+
+        loop {
+            match fut.as_mut().poll(&mut cx) {
+                Poll::Ready(value) => return value,
+                Poll::Pending => continue,
+            }
+        }
+    }
+
+    #[derive(Default, Debug)]
+    struct StringOutput {
+        inner: String,
+    }
+
+    impl Output for StringOutput {
+        fn write(&mut self, value: u8) {
+            self.inner.push(value as char);
+        }
+    }
+
+    impl AsyncOutput for StringOutput {
+        async fn write(&mut self, value: u8) {
+            Output::write(self, value);
+        }
+    }
+
+    type TestBrainfuck = Brainfuck<StdinInput, StringOutput>;
+
+    impl Default for TestBrainfuck {
+        fn default() -> Self {
+            Self {
+                input: StdinInput,
+                output: StringOutput::default(),
+            }
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    fn rdtsc() -> u64 {
+        unsafe { core::arch::x86_64::_rdtsc() }
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn hello_world_speed() {
+        let program = b"++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.";
+        let program = Program::load(program).unwrap();
+
+        let mut brnfk: Brainfuck<StdinInput, StringOutput> = Brainfuck::default();
+
+        let start = rdtsc();
+        brnfk.run(&program, TapeOptions::default()).unwrap();
+        let end = rdtsc();
+
+        let cycles = end - start;
+        let cycles_per_command = cycles / program.commands.len() as u64;
+        println!("{}", cycles_per_command);
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn hello_world() {
+        let program = b"++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.";
+        let program = Program::load(program).unwrap();
+
+        let mut brnfk: Brainfuck<StdinInput, StringOutput> = Brainfuck::default();
+
+        brnfk.run(&program, TapeOptions::default()).unwrap();
+
+        let (_, out) = brnfk.into_inner();
+
+        assert_eq!(out.inner, "Hello World!\n");
+    }
+
+    #[test]
+    fn hello_world_async() {
+        let program = b"++++++++[>++++[>++>+++>+++>+<<<<-]>+>+>->>+[<]<-]>>.>---.+++++++..+++.>>.<-.<.+++.------.--------.>>+.>++.";
+        let program = Program::load(program).unwrap();
+
+        let mut brnfk: Brainfuck<StdinInput, StringOutput> = Brainfuck::default();
+
+        block_on(brnfk.run_async(&program, TapeOptions::default())).unwrap();
+
+        let (_, out) = brnfk.into_inner();
+
+        assert_eq!(out.inner, "Hello World!\n");
+    }
+
+    #[test]
+    fn collapses_clear_loop() {
+        let program = Program::load(b"+++[-]").unwrap();
+        assert_eq!(program.ops, vec![Op::Add(3), Op::Set(0)]);
+    }
+
+    #[test]
+    fn collapses_copy_loop() {
+        let program = Program::load(b"++[->+<]").unwrap();
+        assert_eq!(
+            program.ops,
+            vec![
+                Op::Add(2),
+                Op::MulAdd {
+                    offset: 1,
+                    factor: 1
+                },
+                Op::Set(0),
+            ]
+        );
+    }
+
+    #[test]
+    fn transpiles_to_c_and_asm() {
+        let program = Program::load(b"+.").unwrap();
+
+        let c = program.transpile(Target::C);
+        assert!(c.contains("++*ptr;"));
+        assert!(c.contains("putchar(*ptr);"));
+
+        let asm = program.transpile(Target::Asm);
+        assert!(asm.contains("inc byte [r12]"));
+        assert!(asm.contains("syscall"));
+    }
+
+    #[test]
+    fn reports_location_of_invalid_command_on_second_line() {
+        let err = Program::load(b"+\n+x").unwrap_err();
+
+        match err {
+            Error::InvalidCommand { location, command } => {
+                assert_eq!(command, b'x');
+                assert_eq!(
+                    location,
+                    Location {
+                        line: 2,
+                        column: 2,
+                        byte: 3
+                    }
+                );
+            }
+            other => panic!("expected InvalidCommand, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reports_location_of_unmatched_jump() {
+        let err = Program::load(b"[[]").unwrap_err();
+
+        match err {
+            Error::UnmatchedJump { location } => {
+                assert_eq!(
+                    location,
+                    Location {
+                        line: 1,
+                        column: 1,
+                        byte: 0
+                    }
+                );
+            }
+            other => panic!("expected UnmatchedJump, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn renders_a_caret_under_the_offending_column() {
+        let source = b"+\n+x";
+        let err = Program::load(source).unwrap_err();
+
+        let report = err.report(source);
+        assert!(report.contains("2:2"));
+        assert!(report.contains("+x"));
+        assert!(report.contains(" ^"));
+    }
+
+    #[test]
+    fn dec_ptr_at_zero_is_a_runtime_error() {
+        let program = Program::load(b"<").unwrap();
+        let mut brnfk = TestBrainfuck::default();
+
+        let err = brnfk.run(&program, TapeOptions::default()).unwrap_err();
+        assert_eq!(err, RuntimeError::PointerOutOfBounds { pointer: -1 });
+    }
+
+    #[test]
+    fn wrap_bounds_mode_wraps_the_pointer() {
+        // A 4-cell tape: moving 4 past the start wraps back onto cell 0.
+        let program = Program::load(b">>>>+.").unwrap();
+        let mut brnfk = TestBrainfuck::default();
+
+        let options = TapeOptions {
+            bounds: BoundsMode::Wrap { size: 4 },
+            ..TapeOptions::default()
+        };
+        brnfk.run(&program, options).unwrap();
+
+        let (_, out) = brnfk.into_inner();
+        assert_eq!(out.inner, "\u{1}");
+    }
+
+    #[test]
+    fn wrap_bounds_mode_rejects_zero_size_instead_of_panicking() {
+        let program = Program::load(b"+").unwrap();
+        let mut brnfk = TestBrainfuck::default();
+
+        let options = TapeOptions {
+            bounds: BoundsMode::Wrap { size: 0 },
+            ..TapeOptions::default()
+        };
+        let err = brnfk.run(&program, options).unwrap_err();
+        assert_eq!(err, RuntimeError::PointerOutOfBounds { pointer: 0 });
+    }
+
+    #[test]
+    fn error_bounds_mode_rejects_out_of_range_pointer() {
+        let program = Program::load(b">>>>").unwrap();
+        let mut brnfk = TestBrainfuck::default();
+
+        let options = TapeOptions {
+            bounds: BoundsMode::Error { size: 4 },
+            ..TapeOptions::default()
+        };
+        let err = brnfk.run(&program, options).unwrap_err();
+        assert_eq!(err, RuntimeError::PointerOutOfBounds { pointer: 4 });
+    }
+
+    #[test]
+    fn sixteen_bit_cells_do_not_wrap_at_256() {
+        // `[.-]` outputs the cell, decrements, and repeats until it hits
+        // zero, so the output length is exactly the cell's value. With a
+        // correctly-coalesced run of 256 `+`s under 16-bit cells that's
+        // 256 bytes; if the optimizer wrapped the run at 256 first (8-bit
+        // truncation) the cell would already be 0 and the loop body would
+        // never run.
+        let source = format!("{}{}", "+".repeat(256), "[.-]");
+        let program = Program::load(source.as_bytes()).unwrap();
+        let mut brnfk = TestBrainfuck::default();
+
+        let options = TapeOptions {
+            cell_width: CellWidth::U16,
+            ..TapeOptions::default()
+        };
+        brnfk.run(&program, options).unwrap();
+
+        let (_, out) = brnfk.into_inner();
+        assert_eq!(out.inner.chars().count(), 256);
+    }
+
+    #[test]
+    fn eof_policy_sets_max_value() {
+        let program = Program::load(b",.").unwrap();
+        let mut brnfk = TestBrainfuck::default();
+
+        let options = TapeOptions {
+            eof: EofPolicy::Max,
+            ..TapeOptions::default()
+        };
+        brnfk.run(&program, options).unwrap();
+
+        let (_, out) = brnfk.into_inner();
+        assert_eq!(out.inner, "\u{ff}");
+    }
+}